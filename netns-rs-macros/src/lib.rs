@@ -0,0 +1,62 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Proc macros backing the `test-util` feature of `netns-rs`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, ItemFn};
+
+/// Runs a test function inside a fresh, anonymous network namespace via
+/// [`NetNs::with_temp`](netns_rs::NetNs::with_temp), which is unmounted and
+/// removed again once the test returns or panics.
+///
+/// `#[should_panic]` and `#[ignore]` on the annotated function are forwarded
+/// to the generated `#[test]` unchanged. Like the rest of the crate, the
+/// generated test requires elevated privileges to run.
+///
+/// ```ignore
+/// #[netns_test]
+/// fn configures_loopback(ns: &netns_rs::NetNs) {
+///     // runs inside its own namespace
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn netns_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemFn);
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+    let sig = &input.sig;
+    let block = &input.block;
+    let name = &sig.ident;
+    let output = &sig.output;
+
+    // Reuse whatever pattern the test function declared for its `&NetNs`
+    // parameter (so the body's references to it keep working), or `_` if it
+    // doesn't bind one at all.
+    let param = match sig.inputs.first() {
+        Some(syn::FnArg::Typed(pat_type)) => {
+            let pat = &pat_type.pat;
+            quote! { #pat }
+        }
+        _ => quote! { _ },
+    };
+
+    // Forward the test body's return type and return the `with_temp` result
+    // directly (no trailing `;`) so a `-> Result<(), E>` test body still
+    // reports failures to the test harness instead of having them silently
+    // discarded as a statement.
+    let expanded = quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #name() #output {
+            netns_rs::NetNs::with_temp(|#param| #block).unwrap()
+        }
+    };
+
+    expanded.into()
+}
@@ -44,6 +44,16 @@
 mod netns;
 pub use self::netns::*;
 
+#[cfg(feature = "rtnetlink")]
+mod rtnetlink;
+
+#[cfg(feature = "test-util")]
+mod testing;
+#[cfg(feature = "test-util")]
+pub use self::testing::*;
+#[cfg(feature = "test-util")]
+pub use netns_rs_macros::netns_test;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error)]
@@ -77,4 +87,17 @@ pub enum Error {
 
     #[error("Can not setns, {0}")]
     SetnsError(nix::Error),
+
+    #[error("Failed to fork, {0}")]
+    ForkError(nix::Error),
+
+    #[error("Failed to create pipe, {0}")]
+    PipeError(nix::Error),
+
+    #[error("run_isolated failed, {0}")]
+    IsolatedRunError(String),
+
+    #[cfg(feature = "rtnetlink")]
+    #[error("rtnetlink operation failed, {0}")]
+    RtNetlinkError(String),
 }
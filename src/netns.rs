@@ -12,7 +12,10 @@ use std::thread::{self, JoinHandle};
 
 use nix::mount::{mount, umount2, MntFlags, MsFlags};
 use nix::sched::{setns, unshare, CloneFlags};
-use nix::unistd::gettid;
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{close, fork, gettid, pipe, read, write, ForkResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::{Error, Result};
 
@@ -151,15 +154,59 @@ impl<E: Env> Drop for NetNs<E> {
     }
 }
 
+/// An RAII guard returned by [`NetNs::enter_scoped`].
+///
+/// Restores the calling thread's previous network namespace when dropped.
+///
+/// `setns` only affects the calling thread, so this guard is only meaningful
+/// on the thread that created it: dropping it on another thread (e.g. after
+/// moving it across an `.await` point onto a different worker, or into
+/// another thread pool thread) restores the saved namespace on whatever
+/// thread happens to run the destructor instead of the one that entered,
+/// silently mis-namespacing it and stranding the original thread. `NsGuard`
+/// is therefore `!Send` so it cannot cross threads by construction; callers
+/// needing to run namespaced work from a thread pool or async context should
+/// use [`NetNs::run`] instead, which pins the closure to a dedicated thread.
+#[derive(Debug)]
+pub struct NsGuard {
+    src_ns: NetNs,
+    // `*mut ()` is `!Send` and `!Sync`, which is what makes this guard
+    // thread-affine; it carries no data of its own.
+    _not_send: std::marker::PhantomData<*mut ()>,
+}
+
+impl Drop for NsGuard {
+    fn drop(&mut self) {
+        // Best-effort restore; there's nothing more useful to do with the
+        // error in a Drop impl.
+        let _ = self.src_ns.enter();
+    }
+}
+
 impl<E: Env> NetNs<E> {
     /// Creates a new `NetNs` with the specified name and Env.
     /// The persist dir of network namespace will be created if it doesn't already exist.
     pub fn new_with_env<S: AsRef<str>>(ns_name: S, env: E) -> Result<Self> {
+        Self::create_with_env(ns_name, env, false)
+    }
+
+    fn create_with_env<S: AsRef<str>>(ns_name: S, env: E, exclusive: bool) -> Result<Self> {
         env.init()?;
 
         // create an empty file at the mount point
         let ns_path = env.persist_dir().join(ns_name.as_ref());
-        let _ = File::create(&ns_path).map_err(Error::CreateNsError)?;
+        let _ = if exclusive {
+            // `create_new` opens atomically with O_EXCL, so callers racing on
+            // the same name get a clean `AlreadyExists` instead of silently
+            // sharing or truncating each other's namespace file.
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&ns_path)
+        } else {
+            File::create(&ns_path)
+        }
+        .map_err(Error::CreateNsError)?;
         Self::persistent(&ns_path, true).map_err(|e| {
             // Ensure the mount point is cleaned up on errors; if the namespace
             // was successfully mounted this will have no effect because the file
@@ -170,6 +217,37 @@ impl<E: Env> NetNs<E> {
         Self::get_from_env(ns_name, env)
     }
 
+    /// Creates a new persistent network namespace with a randomly generated
+    /// name of the form `netns-<hex>`, using the given Env.
+    ///
+    /// The name is created atomically (`O_EXCL`); if it collides with an
+    /// existing file in the persist dir, a new name is drawn and creation is
+    /// retried.
+    ///
+    /// Requires elevated privileges.
+    pub fn new_anonymous_with_env(env: E) -> Result<Self>
+    where
+        E: Clone,
+    {
+        const MAX_ATTEMPTS: usize = 10;
+
+        for _ in 0..MAX_ATTEMPTS {
+            let ns_name = format!("netns-{}", random_hex_suffix());
+            match Self::create_with_env(ns_name, env.clone(), true) {
+                Ok(ns) => return Ok(ns),
+                Err(Error::CreateNsError(e)) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    continue
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(Error::CreateNsError(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "exhausted attempts to generate a unique anonymous netns name",
+        )))
+    }
+
     fn persistent<P: AsRef<Path>>(ns_path: &P, new_thread: bool) -> Result<()> {
         if new_thread {
             let ns_path_clone = ns_path.as_ref().to_path_buf();
@@ -231,6 +309,27 @@ impl<E: Env> NetNs<E> {
         setns(self.file.as_raw_fd(), CloneFlags::CLONE_NEWNET).map_err(Error::SetnsError)
     }
 
+    /// Makes the current thread enter this network namespace and returns a
+    /// guard that restores the thread's previous namespace when dropped.
+    ///
+    /// This is the scoped counterpart to [`enter`](Self::enter): it lets
+    /// callers write straight-line code (`let _g = ns.enter_scoped()?;`) and
+    /// rely on the namespace being restored on every exit path, including an
+    /// early `return` or `?`.
+    ///
+    /// The returned [`NsGuard`] is thread-affine: it must be dropped on the
+    /// same thread that called `enter_scoped`, which is why it is `!Send`.
+    ///
+    /// Requires elevated privileges.
+    pub fn enter_scoped(&self) -> Result<NsGuard> {
+        let src_ns = get_from_current_thread()?;
+        self.enter()?;
+        Ok(NsGuard {
+            src_ns,
+            _not_send: std::marker::PhantomData,
+        })
+    }
+
     /// Returns the NetNs with the specified name and Env.
     pub fn get_from_env<S: AsRef<str>>(ns_name: S, env: E) -> Result<Self> {
         let ns_path = env.persist_dir().join(ns_name.as_ref());
@@ -270,10 +369,21 @@ impl<E: Env> NetNs<E> {
 
     /// Run a closure in NetNs, which is specified by name and Env.
     ///
+    /// The closure is executed on a freshly spawned thread dedicated to this
+    /// call, so no other work scheduled on the calling thread pool can ever
+    /// observe the namespace switch. The thread's original namespace is
+    /// restored before returning, even if the closure panics; in that case
+    /// the panic is propagated to the caller after the restore has run.
+    ///
+    /// If the calling thread is already in this namespace, no thread is
+    /// spawned and `f` runs directly on the calling thread.
+    ///
     /// Requires elevated privileges.
     pub fn run<F, T>(&self, f: F) -> Result<T>
     where
-        F: FnOnce(&Self) -> T,
+        F: FnOnce(&Self) -> T + Send,
+        T: Send,
+        E: Sync,
     {
         // get current network namespace
         let src_ns = get_from_current_thread()?;
@@ -282,14 +392,159 @@ impl<E: Env> NetNs<E> {
         if &src_ns == self {
             return Ok(f(self));
         }
-        // enter new namespace
-        self.enter()?;
 
-        let result = f(self);
-        // back to old namespace
-        src_ns.enter()?;
+        thread::scope(|scope| {
+            let handle = scope.spawn(|| -> Result<T> {
+                // enter new namespace
+                self.enter()?;
+
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(self)));
+
+                // back to old namespace, regardless of whether the closure panicked
+                let restore = src_ns.enter();
+
+                match result {
+                    Ok(value) => restore.map(|_| value),
+                    Err(payload) => {
+                        // best-effort restore already happened above; surface the
+                        // panic to the caller instead of the restore error.
+                        let _ = restore;
+                        std::panic::resume_unwind(payload)
+                    }
+                }
+            });
+            match handle.join() {
+                Ok(result) => result,
+                // The only way a spawned thread ends up here is via the
+                // `resume_unwind` above, i.e. `f` panicked; re-raise it on
+                // the caller's thread now that the namespace has been restored.
+                Err(payload) => std::panic::resume_unwind(payload),
+            }
+        })
+    }
+
+    /// Run a closure in this namespace inside a forked child process, leaving
+    /// the parent's namespace untouched.
+    ///
+    /// Unlike [`run`](Self::run), which switches the calling thread's
+    /// namespace, this forks the process, enters the namespace in the child,
+    /// runs `f` there, sends the serialized return value back to the parent
+    /// over a pipe, and exits the child. The parent never calls `setns` at
+    /// all, so this is the right choice when even a transient namespace
+    /// switch on some thread of the current process is unacceptable.
+    ///
+    /// Only async-signal-safe work should happen between the `fork` and the
+    /// point where `f` returns, as is true of any forking API.
+    ///
+    /// Requires elevated privileges.
+    pub fn run_isolated<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> T,
+        T: Serialize + DeserializeOwned,
+    {
+        let (read_fd, write_fd) = pipe().map_err(Error::PipeError)?;
+
+        match unsafe { fork() }.map_err(Error::ForkError)? {
+            ForkResult::Child => {
+                let _ = close(read_fd);
+                let exit_code = match self.enter() {
+                    Ok(()) => {
+                        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+                            Ok(value) => match serde_json::to_vec(&value) {
+                                Ok(bytes) => {
+                                    if write_all(write_fd, &bytes).is_ok() {
+                                        0
+                                    } else {
+                                        1
+                                    }
+                                }
+                                Err(_) => 1,
+                            },
+                            // `f` panicked; surface this as a distinct exit code so the
+                            // parent doesn't mistake it for a serialization failure.
+                            Err(_) => PANIC_EXIT_CODE,
+                        }
+                    }
+                    Err(_) => 1,
+                };
+                let _ = close(write_fd);
+                std::process::exit(exit_code);
+            }
+            ForkResult::Parent { child } => {
+                // Close our copy of the write end before reading, so that if
+                // the child dies without writing anything we see EOF instead
+                // of blocking forever; keep going even if this fails so the
+                // child is still reaped below.
+                let close_result = close(write_fd);
+
+                let mut buf = Vec::new();
+                let read_result = read_all(read_fd, &mut buf);
+                let _ = close(read_fd);
+
+                let status = waitpid(child, None)
+                    .map_err(|e| Error::IsolatedRunError(format!("waitpid failed: {}", e)))?;
+
+                close_result.map_err(|e| {
+                    Error::IsolatedRunError(format!("failed to close pipe: {}", e))
+                })?;
+                read_result?;
+
+                match status {
+                    WaitStatus::Exited(_, 0) => serde_json::from_slice(&buf)
+                        .map_err(|e| Error::IsolatedRunError(format!("failed to deserialize result from child: {}", e))),
+                    WaitStatus::Exited(_, PANIC_EXIT_CODE) => Err(Error::IsolatedRunError(
+                        "closure panicked in isolated child".to_string(),
+                    )),
+                    WaitStatus::Exited(_, code) => Err(Error::IsolatedRunError(format!(
+                        "child exited with non-zero status {}",
+                        code
+                    ))),
+                    WaitStatus::Signaled(_, signal, _) => Err(Error::IsolatedRunError(format!(
+                        "child was killed by signal {}",
+                        signal
+                    ))),
+                    other => Err(Error::IsolatedRunError(format!(
+                        "child ended in unexpected state: {:?}",
+                        other
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+/// Exit code used by the `run_isolated` child to signal that `f` panicked,
+/// distinct from the generic failure code so the parent can report it clearly.
+const PANIC_EXIT_CODE: i32 = 2;
+
+/// Writes the whole buffer to `fd`, retrying on `EINTR`.
+fn write_all(fd: std::os::unix::io::RawFd, mut buf: &[u8]) -> std::result::Result<(), ()> {
+    while !buf.is_empty() {
+        match write(fd, buf) {
+            Ok(0) => return Err(()),
+            Ok(n) => buf = &buf[n..],
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(_) => return Err(()),
+        }
+    }
+    Ok(())
+}
 
-        Ok(result)
+/// Reads `fd` to EOF into `buf`, retrying on `EINTR`.
+fn read_all(fd: std::os::unix::io::RawFd, buf: &mut Vec<u8>) -> Result<()> {
+    let mut chunk = [0u8; 4096];
+    loop {
+        match read(fd, &mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => {
+                return Err(Error::IsolatedRunError(format!(
+                    "failed reading from pipe: {}",
+                    e
+                )))
+            }
+        }
     }
 }
 
@@ -308,6 +563,16 @@ impl NetNs {
         Self::new_with_env(ns_name, DefaultEnv)
     }
 
+    /// Creates a new persistent network namespace with a randomly generated,
+    /// collision-free name, using [`DefaultEnv`].
+    ///
+    /// Requires elevated privileges.
+    ///
+    /// [`DefaultEnv`]: DefaultEnv
+    pub fn new_anonymous() -> Result<Self> {
+        Self::new_anonymous_with_env(DefaultEnv)
+    }
+
     /// Returns the NetNs with the specified name and `DefaultEnv`.
     pub fn get<S: AsRef<str>>(ns_name: S) -> Result<Self> {
         Self::get_from_env(ns_name, DefaultEnv)
@@ -319,7 +584,8 @@ impl NetNs {
     pub fn run_in<S, F, T>(ns_name: S, f: F) -> Result<T>
     where
         S: AsRef<str>,
-        F: FnOnce(&Self) -> T,
+        F: FnOnce(&Self) -> T + Send,
+        T: Send,
     {
         // get network namespace
         let run_ns = Self::get_from_env(ns_name, DefaultEnv)?;
@@ -356,6 +622,12 @@ fn get_current_thread_netns_path() -> PathBuf {
     PathBuf::from(format!("/proc/self/task/{}/ns/net", gettid()))
 }
 
+/// Generates a random hex suffix for anonymous netns names, drawn from a CSPRNG.
+fn random_hex_suffix() -> String {
+    let bytes: [u8; 8] = rand::random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -458,6 +730,26 @@ mod tests {
         assert_ne!(src, new.netns);
     }
 
+    #[test]
+    fn test_netns_enter_scoped() {
+        let new = TestNetNs::new("test_netns_enter_scoped");
+
+        let src = get_from_current_thread().unwrap();
+        assert_ne!(src, new.netns);
+
+        {
+            let _guard = new.netns.enter_scoped().unwrap();
+
+            let cur = get_from_current_thread().unwrap();
+            assert_eq!(new.netns, cur);
+            assert_ne!(src, cur);
+        }
+
+        // dropping the guard must restore the thread's original namespace.
+        let restored = get_from_current_thread().unwrap();
+        assert_eq!(src, restored);
+    }
+
     struct TestEnv;
     impl Env for TestEnv {
         fn persist_dir(&self) -> PathBuf {
@@ -499,4 +791,57 @@ mod tests {
             .unwrap();
         assert!(matches!(ret, Ok(_)));
     }
+
+    #[test]
+    fn test_netns_run_restores_after_panic() {
+        let new = TestNetNs::new("test_netns_run_restores_after_panic");
+
+        let src_ns = get_from_current_thread().unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            new.netns.run(|_| {
+                panic!("boom");
+            })
+        }));
+        assert!(result.is_err());
+
+        // the caller's thread must be back in its original namespace even
+        // though the closure panicked.
+        let cur = get_from_current_thread().unwrap();
+        assert_eq!(src_ns, cur);
+    }
+
+    #[test]
+    fn test_netns_run_isolated() {
+        let new = TestNetNs::new("test_netns_run_isolated");
+
+        let src_ns = get_from_current_thread().unwrap();
+
+        let value = new.netns.run_isolated(|| 42u32).unwrap();
+        assert_eq!(value, 42);
+
+        // run_isolated must never touch the parent's own namespace.
+        let cur = get_from_current_thread().unwrap();
+        assert_eq!(src_ns, cur);
+
+        let err = new
+            .netns
+            .run_isolated(|| -> u32 { panic!("boom") })
+            .unwrap_err();
+        assert!(matches!(err, Error::IsolatedRunError(_)));
+    }
+
+    #[test]
+    fn test_netns_new_anonymous() {
+        let mut ns = NetNs::new_anonymous().unwrap();
+        let file_name = ns.path().file_name().and_then(|n| n.to_str()).unwrap();
+        assert!(file_name.starts_with("netns-"));
+        assert!(ns.path().exists());
+
+        let mut other = NetNs::new_anonymous().unwrap();
+        assert_ne!(ns.path(), other.path());
+
+        ns.umount().unwrap();
+        other.umount().unwrap();
+    }
 }
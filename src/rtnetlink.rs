@@ -0,0 +1,126 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Optional interface provisioning on top of [`rtnetlink`](https://docs.rs/rtnetlink).
+//!
+//! This module is only compiled in when the `rtnetlink` feature is enabled.
+//! It adds a couple of convenience methods on [`NetNs`] so that callers can
+//! stand up a minimally usable network in a namespace without shelling out
+//! to `ip`.
+
+use std::os::unix::io::AsRawFd;
+
+use futures::stream::TryStreamExt;
+use rtnetlink::Handle;
+
+use crate::{Env, Error, NetNs, Result};
+
+impl<E: Env> NetNs<E> {
+    /// Brings the loopback interface (`lo`) up inside this namespace.
+    ///
+    /// Requires elevated privileges.
+    pub fn bring_loopback_up(&self) -> Result<()>
+    where
+        E: Sync,
+    {
+        self.run(|_| block_on(bring_link_up("lo")))?
+    }
+
+    /// Creates a veth pair named `host_name`/`peer_name` in the caller's
+    /// current namespace, then moves `peer_name` into this namespace and
+    /// brings it up there.
+    ///
+    /// Returns the `(host_ifindex, peer_ifindex)` of the created pair, both
+    /// as seen from their respective namespaces.
+    ///
+    /// Requires elevated privileges.
+    pub fn create_veth_pair(&self, host_name: &str, peer_name: &str) -> Result<(u32, u32)>
+    where
+        E: Sync,
+    {
+        let (host_index, peer_index) = block_on(create_veth_pair(host_name, peer_name))?;
+
+        // Move the peer end into this namespace before entering it, then
+        // enter and bring it up there.
+        block_on(move_link_to_ns(peer_index, self.file().as_raw_fd()))?;
+        // The kernel may renumber the peer on the move, so read its index
+        // again from inside this namespace.
+        let peer_index = self.run(move |_| {
+            block_on(async move {
+                bring_link_up(peer_name).await?;
+                let handle = new_handle().await?;
+                link_index_by_name(&handle, peer_name).await
+            })
+        })??;
+
+        Ok((host_index, peer_index))
+    }
+}
+
+fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build the tokio runtime used to drive rtnetlink requests")
+        .block_on(fut)
+}
+
+async fn new_handle() -> Result<Handle> {
+    let (connection, handle, _) =
+        rtnetlink::new_connection().map_err(|e| Error::RtNetlinkError(e.to_string()))?;
+    tokio::spawn(connection);
+    Ok(handle)
+}
+
+async fn link_index_by_name(handle: &Handle, name: &str) -> Result<u32> {
+    handle
+        .link()
+        .get()
+        .match_name(name.to_string())
+        .execute()
+        .try_next()
+        .await
+        .map_err(|e| Error::RtNetlinkError(e.to_string()))?
+        .map(|link| link.header.index)
+        .ok_or_else(|| Error::RtNetlinkError(format!("link {} not found", name)))
+}
+
+async fn bring_link_up(name: &str) -> Result<()> {
+    let handle = new_handle().await?;
+    let index = link_index_by_name(&handle, name).await?;
+    handle
+        .link()
+        .set(index)
+        .up()
+        .execute()
+        .await
+        .map_err(|e| Error::RtNetlinkError(e.to_string()))
+}
+
+async fn create_veth_pair(host_name: &str, peer_name: &str) -> Result<(u32, u32)> {
+    let handle = new_handle().await?;
+    handle
+        .link()
+        .add()
+        .veth(host_name.to_string(), peer_name.to_string())
+        .execute()
+        .await
+        .map_err(|e| Error::RtNetlinkError(e.to_string()))?;
+
+    let host_index = link_index_by_name(&handle, host_name).await?;
+    let peer_index = link_index_by_name(&handle, peer_name).await?;
+    Ok((host_index, peer_index))
+}
+
+async fn move_link_to_ns(index: u32, ns_fd: std::os::unix::io::RawFd) -> Result<()> {
+    let handle = new_handle().await?;
+    handle
+        .link()
+        .set(index)
+        .setns_by_fd(ns_fd)
+        .execute()
+        .await
+        .map_err(|e| Error::RtNetlinkError(e.to_string()))
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2022 Alibaba Cloud
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! Test helpers for running each test in a fresh, auto-cleaned network
+//! namespace.
+//!
+//! Networking tests tend to collide on shared global interfaces like `lo`
+//! when run in parallel, which is exactly why a namespace per test is
+//! worthwhile. This module is only compiled in when the `test-util` feature
+//! is enabled.
+//!
+//! `test-util` requires the `rtnetlink` feature, which [`with_temp`] uses to
+//! bring the namespace's loopback interface up; enable both together
+//! (`test-util` should list `rtnetlink` as a dependent feature in
+//! `Cargo.toml`).
+
+#[cfg(not(feature = "rtnetlink"))]
+compile_error!("the `test-util` feature requires the `rtnetlink` feature to bring loopback up in `NetNs::with_temp`");
+
+use crate::{DefaultEnv, NetNs, Result};
+
+impl NetNs<DefaultEnv> {
+    /// Runs `f` inside a freshly created anonymous namespace, tearing the
+    /// namespace down again once `f` returns, even if it panics.
+    ///
+    /// The namespace is entered on a dedicated thread via
+    /// [`run`](NetNs::run), so no other thread of the process is affected,
+    /// and loopback is brought up before `f` runs.
+    ///
+    /// Requires elevated privileges and the `rtnetlink` feature (used to
+    /// bring loopback up).
+    pub fn with_temp<F, T>(f: F) -> Result<T>
+    where
+        F: FnOnce(&NetNs) -> T + Send,
+        T: Send,
+    {
+        // Ensures the namespace is torn down on every exit path, including
+        // an `f` that panics and unwinds straight out of this function.
+        struct Teardown(NetNs);
+        impl Drop for Teardown {
+            fn drop(&mut self) {
+                let _ = self.0.umount();
+            }
+        }
+
+        let guard = Teardown(NetNs::new_anonymous()?);
+        guard.0.run(|ns| ns.bring_loopback_up())??;
+        guard.0.run(f)
+    }
+}